@@ -1,8 +1,12 @@
+mod mpt;
+
 use anyhow::Result;
-use clap::Parser;
-use ethers::providers::{Http, Provider};
-use ethers::types::{H256, U64};
-use sha3::{Digest, Keccak256};
+use cita_trie::{MemoryDB, PatriciaTrie, Trie};
+use clap::{Parser, Subcommand};
+use ethers::providers::{Http, Middleware, Provider};
+use ethers::types::{Address, EIP1186ProofResponse, Log, TransactionReceipt, H256, U64};
+use ethers::utils::{hex, rlp};
+use hasher::HasherKeccak;
 use std::sync::Arc;
 use std::str::FromStr;
 use tracing::{info, warn, error};
@@ -13,17 +17,75 @@ struct Args {
     #[arg(short, long)]
     rpc_url: Option<String>,
 
-    // Block number to verify
-    #[arg(short, long)]
-    block: u64,
+    #[command(subcommand)]
+    command: Command,
+}
 
-    // Transaction hash to verify
-    #[arg(short, long)]
-    tx_hash: String,
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Verify that a transaction receipt is included in a block's receipts trie.
+    Verify {
+        // Block number to verify
+        #[arg(short, long)]
+        block: u64,
 
-    // Merkle proof as comma-separated hex strings
-    #[arg(short, long)]
-    proof: String,
+        // Transaction hash to verify
+        #[arg(short, long)]
+        tx_hash: String,
+
+        // Merkle proof as comma-separated hex strings
+        #[arg(short, long)]
+        proof: String,
+    },
+
+    /// Reconstruct a block's receipts trie and emit an inclusion proof.
+    GenerateProof {
+        // Block number to build the receipts trie for
+        #[arg(short, long)]
+        block: u64,
+
+        // Transaction index within the block to prove
+        #[arg(short, long)]
+        tx_index: u64,
+    },
+
+    /// Verify an account (and optional storage slots) against the state root.
+    Account {
+        // Block number to verify against
+        #[arg(short, long)]
+        block: u64,
+
+        // Account address to verify
+        #[arg(short, long)]
+        address: String,
+
+        // Storage slots to verify (repeat --slot for each)
+        #[arg(short, long = "slot")]
+        slots: Vec<String>,
+    },
+
+    /// Verify that a specific log of a transaction is included in a block.
+    Log {
+        // Block number to verify
+        #[arg(short, long)]
+        block: u64,
+
+        // Transaction hash whose receipt carries the log
+        #[arg(short, long)]
+        tx_hash: String,
+
+        // Index of the log within the receipt
+        #[arg(short, long)]
+        log_index: usize,
+
+        // Receipt inclusion proof as comma-separated hex strings
+        #[arg(short, long)]
+        proof: String,
+
+        // Maximum number of logs processed per receipt
+        #[arg(short, long, default_value_t = MAX_SUPPORTED_LOGS_NUMBER)]
+        max_logs: usize,
+    },
 }
 
 struct MerkleVerifier {
@@ -45,49 +107,230 @@ impl MerkleVerifier {
         Ok(block.receipts_root)
     }
 
-    async fn get_receipt(&self, tx_hash: H256) -> Result<Vec<u8>> {
+    async fn get_receipt(&self, tx_hash: H256) -> Result<TransactionReceipt> {
         let receipt = self.provider.get_transaction_receipt(tx_hash).await?
             .ok_or_else(|| anyhow::anyhow!("Transaction receipt not found for {:?}", tx_hash))?;
-        
-        // In a real implementation, we would RLP encode the receipt here
-        // For now, we'll just use a placeholder hash
-        let receipt_data = Keccak256::digest(&receipt.to_string()).to_vec();
-        info!("Retrieved and hashed receipt for transaction {:?}", tx_hash);
-        Ok(receipt_data)
+        info!("Retrieved receipt for transaction {:?}", tx_hash);
+        Ok(receipt)
     }
 
-    async fn verify_receipt_proof(&self, block_number: U64, tx_hash: H256, proof: Vec<H256>) -> Result<bool> {
+    async fn verify_receipt_proof(&self, block_number: U64, tx_hash: H256, proof: Vec<Vec<u8>>) -> Result<bool> {
         info!("Verifying receipt proof for tx {:?} in block {}", tx_hash, block_number);
         let receipts_root = self.get_block_receipts_root(block_number).await?;
-        let receipt_data = self.get_receipt(tx_hash).await?;
-        let receipt_hash = H256::from_slice(&Keccak256::digest(&receipt_data));
-        
-        let is_valid = self.verify_merkle_proof(receipt_hash, proof.clone(), receipts_root);
+        let receipt = self.get_receipt(tx_hash).await?;
+
+        // Receipts are keyed in the trie by the RLP-encoded transaction index.
+        let tx_index = receipt.transaction_index;
+        let key = rlp::encode(&tx_index.as_u64()).to_vec();
+        let expected_value = encode_receipt(&receipt);
+
+        let value = mpt::verify_proof(receipts_root, &key, &proof)?;
+        let is_valid = value == expected_value;
+
         info!("Proof verification result: {}", is_valid);
-        info!("Receipt hash: {:?}", receipt_hash);
+        info!("Transaction index: {}", tx_index);
         info!("Proof length: {}", proof.len());
-        
+
         Ok(is_valid)
     }
 
-    fn verify_merkle_proof(&self, leaf: H256, proof: Vec<H256>, root: H256) -> bool {
-        let mut current = leaf;
-        
-        for (i, sibling) in proof.iter().enumerate() {
-            let mut combined = Vec::with_capacity(64);
-            if current < *sibling {
-                combined.extend_from_slice(&current.0);
-                combined.extend_from_slice(&sibling.0);
-            } else {
-                combined.extend_from_slice(&sibling.0);
-                combined.extend_from_slice(&current.0);
-            }
-            
-            current = H256::from_slice(&Keccak256::digest(&combined));
-            info!("Proof step {}: Combined hash {:?}", i + 1, current);
+    /// Reconstruct the full receipts trie for a block and extract the inclusion
+    /// proof for `tx_index`, returned as the comma-separated hex the verifier
+    /// consumes.
+    ///
+    /// Every receipt in the block is RLP-encoded and inserted into an in-memory
+    /// Merkle-Patricia Trie keyed by `rlp(index)`; the recomputed root is
+    /// checked against the block's `receipts_root` before the path to the
+    /// target index is returned.
+    async fn generate_receipt_proof(&self, block_number: U64, tx_index: u64) -> Result<String> {
+        info!("Reconstructing receipts trie for block {}", block_number);
+        let receipts_root = self.get_block_receipts_root(block_number).await?;
+        let receipts = self.provider.get_block_receipts(block_number).await?;
+        info!("Fetched {} receipts for block {}", receipts.len(), block_number);
+
+        let memdb = Arc::new(MemoryDB::new(true));
+        let hasher = Arc::new(HasherKeccak::new());
+        let mut trie = PatriciaTrie::new(memdb, hasher);
+        for receipt in &receipts {
+            let key = rlp::encode(&receipt.transaction_index.as_u64()).to_vec();
+            trie.insert(key, encode_receipt(receipt))?;
+        }
+
+        let root = trie.root()?;
+        if H256::from_slice(&root) != receipts_root {
+            return Err(anyhow::anyhow!(
+                "reconstructed receipts root {:?} does not match block receipts_root {:?}",
+                H256::from_slice(&root),
+                receipts_root
+            ));
+        }
+
+        let key = rlp::encode(&tx_index).to_vec();
+        let proof = trie.get_proof(&key)?;
+        info!("Extracted proof of {} nodes for tx index {}", proof.len(), tx_index);
+
+        Ok(proof
+            .iter()
+            .map(|node| format!("0x{}", hex::encode(node)))
+            .collect::<Vec<_>>()
+            .join(","))
+    }
+
+    /// Verify an account and any requested storage slots via `eth_getProof`.
+    ///
+    /// The account is RLP-encoded as `[nonce, balance, storage_root,
+    /// code_hash]` and walked against the block's `state_root` with key
+    /// `keccak256(address)`; each storage slot is walked against the account's
+    /// `storage_root` with key `keccak256(slot)`.
+    async fn verify_account_proof(&self, block_number: U64, address: Address, slots: Vec<H256>) -> Result<bool> {
+        info!("Verifying account {:?} in block {}", address, block_number);
+        let block = self.provider.get_block(block_number).await?
+            .ok_or_else(|| anyhow::anyhow!("Block {} not found", block_number))?;
+        let state_root = block.state_root;
+
+        let proof = self.provider.get_proof(address, slots, Some(block_number.into())).await?;
+
+        let key = mpt::keccak256(address.as_bytes());
+        let account_proof: Vec<Vec<u8>> = proof.account_proof.iter().map(|node| node.to_vec()).collect();
+        let value = mpt::verify_proof(state_root, &key, &account_proof)?;
+        let mut is_valid = value == encode_account(&proof);
+        info!("Account proof valid: {}", is_valid);
+
+        for slot_proof in &proof.storage_proof {
+            let slot_key = mpt::keccak256(slot_proof.key.as_bytes());
+            let slot_nodes: Vec<Vec<u8>> = slot_proof.proof.iter().map(|node| node.to_vec()).collect();
+            let slot_value = mpt::verify_proof(proof.storage_hash, &slot_key, &slot_nodes)?;
+            let slot_ok = slot_value == rlp::encode(&slot_proof.value).to_vec();
+            info!("Storage slot {:?} proof valid: {}", slot_proof.key, slot_ok);
+            is_valid &= slot_ok;
+        }
+
+        Ok(is_valid)
+    }
+
+    /// Verify that a single log is part of the canonical chain: prove the
+    /// receipt's MPT inclusion against `receipts_root`, then confirm the
+    /// claimed log is byte-for-byte present in the verified receipt's logs.
+    ///
+    /// A receipt carrying more than `max_logs` logs is rejected with an error
+    /// before the proof is walked.
+    async fn verify_log_inclusion(&self, block_number: U64, tx_hash: H256, log_index: usize, proof: Vec<Vec<u8>>, max_logs: usize) -> Result<bool> {
+        info!("Verifying log {} of tx {:?} in block {}", log_index, tx_hash, block_number);
+        let receipts_root = self.get_block_receipts_root(block_number).await?;
+        let receipt = self.get_receipt(tx_hash).await?;
+
+        if receipt.logs.len() > max_logs {
+            return Err(anyhow::anyhow!(
+                "receipt has {} logs, exceeding the configured limit of {}",
+                receipt.logs.len(),
+                max_logs
+            ));
+        }
+
+        // Step 1: prove the receipt is included in the receipts trie.
+        let key = rlp::encode(&receipt.transaction_index.as_u64()).to_vec();
+        let value = mpt::verify_proof(receipts_root, &key, &proof)?;
+        if value != encode_receipt(&receipt) {
+            warn!("Receipt does not match its MPT inclusion proof");
+            return Ok(false);
+        }
+
+        // Step 2: confirm the claimed log is present in the verified receipt.
+        let log = receipt.logs.get(log_index).ok_or_else(|| {
+            anyhow::anyhow!("log index {} out of range ({} logs)", log_index, receipt.logs.len())
+        })?;
+        let claimed = encode_log(log);
+        let present = decode_receipt_logs(&value)?.iter().any(|encoded| *encoded == claimed);
+
+        info!("Log {} present in verified receipt: {}", log_index, present);
+        Ok(present)
+    }
+}
+
+/// Default for `--max-logs`: the most logs a receipt may carry for log verification.
+const MAX_SUPPORTED_LOGS_NUMBER: usize = 5;
+
+/// Parse a proof from comma-separated hex strings, each an RLP-encoded trie node.
+fn parse_proof(proof: &str) -> Result<Vec<Vec<u8>>> {
+    proof.split(',')
+        .map(|s| hex::decode(s.trim().trim_start_matches("0x")))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| anyhow::anyhow!("Invalid proof format: {}", e))
+}
+
+/// RLP-encode a single log as `[address, topics, data]`.
+fn encode_log(log: &Log) -> Vec<u8> {
+    let mut stream = rlp::RlpStream::new();
+    stream.begin_list(3);
+    stream.append(&log.address);
+    stream.begin_list(log.topics.len());
+    for topic in &log.topics {
+        stream.append(topic);
+    }
+    let data: &[u8] = log.data.as_ref();
+    stream.append(&data);
+    stream.out().to_vec()
+}
+
+/// Extract the RLP-encoded logs from an encoded receipt value, stripping the
+/// EIP-2718 type byte for typed receipts.
+fn decode_receipt_logs(value: &[u8]) -> Result<Vec<Vec<u8>>> {
+    let payload = match value.first() {
+        Some(&byte) if byte < 0xc0 => &value[1..],
+        _ => value,
+    };
+    let logs = rlp::Rlp::new(payload).at(3)?;
+    let mut encoded = Vec::with_capacity(logs.item_count()?);
+    for i in 0..logs.item_count()? {
+        encoded.push(logs.at(i)?.as_raw().to_vec());
+    }
+    Ok(encoded)
+}
+
+/// RLP-encode an account as it is stored in the state trie: `[nonce, balance,
+/// storage_root, code_hash]`.
+fn encode_account(proof: &EIP1186ProofResponse) -> Vec<u8> {
+    let mut stream = rlp::RlpStream::new();
+    stream.begin_list(4);
+    stream.append(&proof.nonce);
+    stream.append(&proof.balance);
+    stream.append(&proof.storage_hash);
+    stream.append(&proof.code_hash);
+    stream.out().to_vec()
+}
+
+/// Encode a transaction receipt as it is stored in the receipts trie.
+///
+/// The RLP payload is the list `[status_or_post_state, cumulative_gas_used,
+/// logs_bloom, logs]`, where each log is `[address, topics, data]`. For typed
+/// receipts (EIP-2718) the transaction-type byte is prepended to the payload;
+/// legacy (type 0) receipts use the bare RLP list.
+fn encode_receipt(receipt: &TransactionReceipt) -> Vec<u8> {
+    let mut stream = rlp::RlpStream::new();
+    stream.begin_list(4);
+
+    // Pre-Byzantium receipts carry a post-state root; later ones a status flag.
+    match receipt.root {
+        Some(root) => stream.append(&root),
+        None => stream.append(&receipt.status.map(|s| s.as_u64()).unwrap_or_default()),
+    };
+    stream.append(&receipt.cumulative_gas_used);
+    stream.append(&receipt.logs_bloom);
+
+    stream.begin_list(receipt.logs.len());
+    for log in &receipt.logs {
+        stream.append_raw(&encode_log(log), 1);
+    }
+
+    let payload = stream.out().to_vec();
+    match receipt.transaction_type.map(|t| t.as_u64()).unwrap_or_default() {
+        0 => payload,
+        tx_type => {
+            let mut encoded = Vec::with_capacity(payload.len() + 1);
+            encoded.push(tx_type as u8);
+            encoded.extend_from_slice(&payload);
+            encoded
         }
-        
-        current == root
     }
 }
 
@@ -112,41 +355,126 @@ async fn main() -> Result<()> {
 
     info!("Connecting to Ethereum node...");
     let verifier = MerkleVerifier::new(&rpc_url)?;
-    let block_number = U64::from(args.block);
-    
-    let tx_hash = match H256::from_str(&args.tx_hash) {
-        Ok(hash) => hash,
-        Err(e) => {
-            error!("Invalid transaction hash format: {}", e);
-            return Err(anyhow::anyhow!("Invalid transaction hash"));
+
+    match args.command {
+        Command::Verify { block, tx_hash, proof } => {
+            let block_number = U64::from(block);
+
+            let tx_hash = match H256::from_str(&tx_hash) {
+                Ok(hash) => hash,
+                Err(e) => {
+                    error!("Invalid transaction hash format: {}", e);
+                    return Err(anyhow::anyhow!("Invalid transaction hash"));
+                }
+            };
+
+            let proof = match parse_proof(&proof) {
+                Ok(p) => p,
+                Err(e) => {
+                    error!("{}", e);
+                    return Err(e);
+                }
+            };
+
+            match verifier.verify_receipt_proof(block_number, tx_hash, proof).await {
+                Ok(true) => {
+                    info!("✅ Merkle proof verification successful!");
+                    info!("Transaction receipt is included in block {}", block);
+                }
+                Ok(false) => {
+                    warn!("❌ Merkle proof verification failed!");
+                    warn!("Transaction receipt is NOT included in block {}", block);
+                }
+                Err(e) => {
+                    error!("Error verifying proof: {}", e);
+                    return Err(e);
+                }
+            }
         }
-    };
-    
-    // Parse proof from comma-separated hex strings
-    let proof: Vec<H256> = match args.proof.split(',')
-        .map(|s| H256::from_str(s.trim()))
-        .collect::<Result<Vec<_>, _>>() {
-            Ok(p) => p,
-            Err(e) => {
-                error!("Invalid proof format: {}", e);
-                return Err(anyhow::anyhow!("Invalid proof format"));
+        Command::GenerateProof { block, tx_index } => {
+            let block_number = U64::from(block);
+            match verifier.generate_receipt_proof(block_number, tx_index).await {
+                Ok(proof) => {
+                    info!("Generated receipt proof for tx index {} in block {}", tx_index, block);
+                    println!("{}", proof);
+                }
+                Err(e) => {
+                    error!("Error generating proof: {}", e);
+                    return Err(e);
+                }
             }
-        };
-
-    match verifier.verify_receipt_proof(block_number, tx_hash, proof).await {
-        Ok(true) => {
-            info!("✅ Merkle proof verification successful!");
-            info!("Transaction receipt is included in block {}", args.block);
         }
-        Ok(false) => {
-            warn!("❌ Merkle proof verification failed!");
-            warn!("Transaction receipt is NOT included in block {}", args.block);
+        Command::Account { block, address, slots } => {
+            let block_number = U64::from(block);
+
+            let address = match Address::from_str(&address) {
+                Ok(address) => address,
+                Err(e) => {
+                    error!("Invalid address format: {}", e);
+                    return Err(anyhow::anyhow!("Invalid address"));
+                }
+            };
+
+            let slots: Vec<H256> = match slots.iter()
+                .map(|s| H256::from_str(s.trim()))
+                .collect::<Result<Vec<_>, _>>() {
+                    Ok(s) => s,
+                    Err(e) => {
+                        error!("Invalid storage slot format: {}", e);
+                        return Err(anyhow::anyhow!("Invalid storage slot"));
+                    }
+                };
+
+            match verifier.verify_account_proof(block_number, address, slots).await {
+                Ok(true) => {
+                    info!("✅ Account proof verification successful!");
+                    info!("Account {:?} is included in the state of block {}", address, block);
+                }
+                Ok(false) => {
+                    warn!("❌ Account proof verification failed!");
+                    warn!("Account {:?} does NOT match the state of block {}", address, block);
+                }
+                Err(e) => {
+                    error!("Error verifying account proof: {}", e);
+                    return Err(e);
+                }
+            }
         }
-        Err(e) => {
-            error!("Error verifying proof: {}", e);
-            return Err(e);
+        Command::Log { block, tx_hash, log_index, proof, max_logs } => {
+            let block_number = U64::from(block);
+
+            let tx_hash = match H256::from_str(&tx_hash) {
+                Ok(hash) => hash,
+                Err(e) => {
+                    error!("Invalid transaction hash format: {}", e);
+                    return Err(anyhow::anyhow!("Invalid transaction hash"));
+                }
+            };
+
+            let proof = match parse_proof(&proof) {
+                Ok(p) => p,
+                Err(e) => {
+                    error!("{}", e);
+                    return Err(e);
+                }
+            };
+
+            match verifier.verify_log_inclusion(block_number, tx_hash, log_index, proof, max_logs).await {
+                Ok(true) => {
+                    info!("✅ Log inclusion verification successful!");
+                    info!("Log {} of tx {:?} is included in block {}", log_index, tx_hash, block);
+                }
+                Ok(false) => {
+                    warn!("❌ Log inclusion verification failed!");
+                    warn!("Log {} of tx {:?} is NOT included in block {}", log_index, tx_hash, block);
+                }
+                Err(e) => {
+                    error!("Error verifying log inclusion: {}", e);
+                    return Err(e);
+                }
+            }
         }
     }
-    
+
     Ok(())
-} 
\ No newline at end of file
+}
\ No newline at end of file
@@ -0,0 +1,137 @@
+//! Merkle-Patricia-Trie proof verification.
+//!
+//! Ethereum stores receipts, accounts and storage in hexary Merkle-Patricia
+//! Tries rather than balanced binary trees, so inclusion proofs are an ordered
+//! list of RLP-encoded trie nodes (as returned by `eth_getProof` / RPC) walked
+//! from the root down to the leaf. This module implements that walk, mirroring
+//! helios's `verify_proof`.
+
+use anyhow::{anyhow, Result};
+use ethers::types::H256;
+use ethers::utils::rlp::Rlp;
+use sha3::{Digest, Keccak256};
+
+/// Keccak256 of `bytes`.
+pub fn keccak256(bytes: &[u8]) -> [u8; 32] {
+    let mut out = [0u8; 32];
+    out.copy_from_slice(&Keccak256::digest(bytes));
+    out
+}
+
+/// Expand a byte key into its nibble (half-byte) representation.
+fn to_nibbles(key: &[u8]) -> Vec<u8> {
+    let mut nibbles = Vec::with_capacity(key.len() * 2);
+    for byte in key {
+        nibbles.push(byte >> 4);
+        nibbles.push(byte & 0x0f);
+    }
+    nibbles
+}
+
+/// Decode a compact (hex-prefix) encoded path, returning whether the node is a
+/// leaf (terminating) and the path nibbles it consumes.
+fn decode_compact(compact: &[u8]) -> Result<(bool, Vec<u8>)> {
+    let first = *compact
+        .first()
+        .ok_or_else(|| anyhow!("empty compact path"))?;
+    let flag = first >> 4;
+    let is_leaf = flag & 0b10 != 0;
+    let odd = flag & 0b01 != 0;
+
+    let mut nibbles = Vec::new();
+    if odd {
+        nibbles.push(first & 0x0f);
+    }
+    for byte in &compact[1..] {
+        nibbles.push(byte >> 4);
+        nibbles.push(byte & 0x0f);
+    }
+    Ok((is_leaf, nibbles))
+}
+
+/// A reference from a parent node to its child: either a 32-byte hash pointing
+/// at the next proof entry, an inlined (embedded) node under 32 bytes, or an
+/// empty slot.
+enum NodeRef {
+    Hash([u8; 32]),
+    Embedded(Vec<u8>),
+    Empty,
+}
+
+/// Resolve a branch/extension child item into a [`NodeRef`].
+fn child_ref(item: Rlp) -> Result<NodeRef> {
+    if item.is_list() {
+        return Ok(NodeRef::Embedded(item.as_raw().to_vec()));
+    }
+    let data = item.data()?;
+    match data.len() {
+        0 => Ok(NodeRef::Empty),
+        32 => {
+            let mut hash = [0u8; 32];
+            hash.copy_from_slice(data);
+            Ok(NodeRef::Hash(hash))
+        }
+        other => Err(anyhow!("unexpected child reference length {}", other)),
+    }
+}
+
+/// Walk `proof` from `root`, consuming `key` nibble by nibble, and return the
+/// value stored at `key`.
+///
+/// Each referenced node is hashed with Keccak256 and checked against the hash
+/// the parent expects; nodes shorter than 32 bytes are inlined in the parent
+/// rather than referenced by hash and are handled without consuming a proof
+/// entry. The walk succeeds once the key is fully consumed at a terminating
+/// leaf (or the value slot of a branch).
+pub fn verify_proof(root: H256, key: &[u8], proof: &[Vec<u8>]) -> Result<Vec<u8>> {
+    let nibbles = to_nibbles(key);
+    let mut next = NodeRef::Hash(root.0);
+    let mut proof_idx = 0usize;
+    let mut pos = 0usize;
+
+    loop {
+        let node_rlp = match next {
+            NodeRef::Empty => return Err(anyhow!("proof reaches an empty node before the key is consumed")),
+            NodeRef::Embedded(bytes) => bytes,
+            NodeRef::Hash(expected) => {
+                let node = proof
+                    .get(proof_idx)
+                    .ok_or_else(|| anyhow!("proof ended before the key was consumed"))?;
+                if keccak256(node) != expected {
+                    return Err(anyhow!("node hash mismatch at proof index {}", proof_idx));
+                }
+                proof_idx += 1;
+                node.clone()
+            }
+        };
+
+        let rlp = Rlp::new(&node_rlp);
+        match rlp.item_count()? {
+            // Branch node: 16 child slots plus a value slot.
+            17 => {
+                if pos == nibbles.len() {
+                    return Ok(rlp.at(16)?.data()?.to_vec());
+                }
+                let nibble = nibbles[pos] as usize;
+                pos += 1;
+                next = child_ref(rlp.at(nibble)?)?;
+            }
+            // Extension or leaf node: [compact path, child-or-value].
+            2 => {
+                let (is_leaf, path) = decode_compact(rlp.at(0)?.data()?)?;
+                if pos + path.len() > nibbles.len() || nibbles[pos..pos + path.len()] != path[..] {
+                    return Err(anyhow!("path mismatch while walking the trie"));
+                }
+                pos += path.len();
+                if is_leaf {
+                    if pos != nibbles.len() {
+                        return Err(anyhow!("leaf reached but the key was not fully consumed"));
+                    }
+                    return Ok(rlp.at(1)?.data()?.to_vec());
+                }
+                next = child_ref(rlp.at(1)?)?;
+            }
+            other => return Err(anyhow!("invalid trie node with {} items", other)),
+        }
+    }
+}